@@ -0,0 +1,28 @@
+use crate::Editor;
+
+/// A Normal-mode command bound to a key. Plain `fn` pointers keep the
+/// keymap a simple `HashMap<Key, Action>` with no boxing or dynamic dispatch.
+pub type Action = fn(&mut Editor);
+
+/// Resolves a config-file action name (e.g. `"move_next_word_start"`) to the
+/// `Editor` method it names. Returns `None` for an unknown name.
+pub fn resolve(name: &str) -> Option<Action> {
+	let action: Action = match name {
+		"move_left" => Editor::move_left,
+		"move_right" => Editor::move_right,
+		"move_up" => Editor::move_up,
+		"move_down" => Editor::move_down,
+		"move_next_word_start" => Editor::move_next_word_start,
+		"move_prev_word_start" => Editor::move_prev_word_start,
+		"move_next_word_end" => Editor::move_next_word_end,
+		"enter_insert_mode" => Editor::enter_insert_mode,
+		"enter_command_mode" => Editor::enter_command_mode,
+		"enter_search_mode" => Editor::enter_search_mode,
+		"undo" => Editor::undo,
+		"redo" => Editor::redo,
+		"search_next_forward" => Editor::search_next_forward,
+		"search_next_backward" => Editor::search_next_backward,
+		_ => return None,
+	};
+	Some(action)
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use termion::event::Key;
+
+use crate::actions::{self, Action};
+
+/// Status-line and gutter colors, stored as ANSI palette indices so `draw`
+/// can hand them straight to `termion::color::AnsiValue`.
+pub struct Theme {
+	pub status_fg: u8,
+	pub status_bg: u8,
+	pub gutter_fg: u8,
+	pub gutter_bg: u8,
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		Theme {
+			status_fg: 0,
+			status_bg: 7,
+			gutter_fg: 8,
+			gutter_bg: 0,
+		}
+	}
+}
+
+pub struct Config {
+	pub keymap: HashMap<Key, Action>,
+	pub theme: Theme,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			keymap: default_keymap(),
+			theme: Theme::default(),
+		}
+	}
+}
+
+fn default_keymap() -> HashMap<Key, Action> {
+	let mut map = HashMap::new();
+	map.insert(Key::Char('h'), actions::resolve("move_left").unwrap());
+	map.insert(Key::Char('l'), actions::resolve("move_right").unwrap());
+	map.insert(Key::Char('k'), actions::resolve("move_up").unwrap());
+	map.insert(Key::Char('j'), actions::resolve("move_down").unwrap());
+	map.insert(Key::Char('w'), actions::resolve("move_next_word_start").unwrap());
+	map.insert(Key::Char('b'), actions::resolve("move_prev_word_start").unwrap());
+	map.insert(Key::Char('e'), actions::resolve("move_next_word_end").unwrap());
+	map.insert(Key::Char('i'), actions::resolve("enter_insert_mode").unwrap());
+	map.insert(Key::Char(':'), actions::resolve("enter_command_mode").unwrap());
+	map.insert(Key::Char('/'), actions::resolve("enter_search_mode").unwrap());
+	map.insert(Key::Char('u'), actions::resolve("undo").unwrap());
+	map.insert(Key::Ctrl('r'), actions::resolve("redo").unwrap());
+	map.insert(Key::Char('n'), actions::resolve("search_next_forward").unwrap());
+	map.insert(Key::Char('N'), actions::resolve("search_next_backward").unwrap());
+	map
+}
+
+/// Loads `$XDG_CONFIG_HOME/mshed/config.toml` (or `~/.config/mshed/config.toml`),
+/// applying `[keybindings]` and `[theme]` overrides on top of the defaults.
+/// A missing file, or one that can't be read, leaves the defaults in place.
+pub fn load() -> Config {
+	let mut config = Config::default();
+
+	let Some(path) = config_path() else {
+		return config;
+	};
+	let Ok(contents) = fs::read_to_string(&path) else {
+		return config;
+	};
+
+	let mut section = String::new();
+	for line in contents.lines() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		if line.starts_with('[') && line.ends_with(']') {
+			section = line[1..line.len() - 1].to_string();
+			continue;
+		}
+		let Some((key, value)) = line.split_once('=') else {
+			continue;
+		};
+		let key = key.trim().trim_matches('"');
+		let value = value.trim().trim_matches('"');
+
+		match section.as_str() {
+			"keybindings" => {
+				if let (Some(key), Some(action)) = (parse_key(key), actions::resolve(value)) {
+					config.keymap.insert(key, action);
+				}
+			}
+			"theme" => apply_theme_setting(&mut config.theme, key, value),
+			_ => {}
+		}
+	}
+
+	config
+}
+
+fn config_path() -> Option<PathBuf> {
+	let base = env::var("XDG_CONFIG_HOME")
+		.map(PathBuf::from)
+		.or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+		.ok()?;
+	Some(base.join("mshed").join("config.toml"))
+}
+
+/// Parses a config-file key name like `"w"`, `"Ctrl-r"`, or `"Esc"` into a
+/// `termion::event::Key`.
+fn parse_key(name: &str) -> Option<Key> {
+	if let Some(rest) = name.strip_prefix("Ctrl-") {
+		return rest.chars().next().map(Key::Ctrl);
+	}
+	match name {
+		"Esc" => Some(Key::Esc),
+		"Enter" => Some(Key::Char('\n')),
+		_ if name.chars().count() == 1 => name.chars().next().map(Key::Char),
+		_ => None,
+	}
+}
+
+fn apply_theme_setting(theme: &mut Theme, key: &str, value: &str) {
+	let Ok(color) = value.parse::<u8>() else {
+		return;
+	};
+	match key {
+		"status_fg" => theme.status_fg = color,
+		"status_bg" => theme.status_bg = color,
+		"gutter_fg" => theme.gutter_fg = color,
+		"gutter_bg" => theme.gutter_bg = color,
+		_ => {}
+	}
+}
@@ -1,57 +1,291 @@
+mod actions;
+mod config;
+
 use std::env;
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
 use std::process;
+use std::sync::mpsc;
+use std::thread;
+use ropey::Rope;
+use signal_hook::consts::SIGWINCH;
+use signal_hook::iterator::Signals;
+use termion::color;
 use termion::cursor;
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
 use termion::terminal_size;
 
+use config::Config;
+
 #[derive(Debug)]
 enum Mode {
 	Normal,
 	Insert,
 	Command,
+	Search,
+}
+
+/// Controls how the line-number gutter renders, toggled via `:set`.
+#[derive(PartialEq, Eq)]
+enum NumberMode {
+	Off,
+	Absolute,
+	Relative,
 }
 
+#[derive(PartialEq, Eq)]
+enum CharClass {
+	Whitespace,
+	Word,
+	Punct,
+}
+
+fn char_class(c: char) -> CharClass {
+	if c.is_whitespace() {
+		CharClass::Whitespace
+	} else if c.is_alphanumeric() || c == '_' {
+		CharClass::Word
+	} else {
+		CharClass::Punct
+	}
+}
+
+/// A point-in-time copy of the buffer and cursor, pushed onto the undo/redo
+/// stacks before a destructive edit.
+struct Snapshot {
+	buffer: Rope,
+	cursor_x: usize,
+	cursor_y: usize,
+}
+
+/// Caps how many snapshots `undo_stack`/`redo_stack` keep around so undo
+/// history can't grow without bound on a long editing session.
+const UNDO_LIMIT: usize = 1000;
+
+/// How many consecutive `:q` are refused while there are unsaved changes;
+/// the next consecutive `:q` after those actually quits.
+const QUIT_TIMES: u8 = 1;
+
 struct Editor {
 	mode: Mode,
 	cursor_x: usize,
 	cursor_y: usize,
-	buffer: Vec<String>,
+	buffer: Rope,
 	command_buffer: String,
 	filename: Option<String>,
 	scroll_x: usize,
 	scroll_y: usize,
+	undo_stack: Vec<Snapshot>,
+	redo_stack: Vec<Snapshot>,
+	undo_limit: usize,
+	search_buffer: String,
+	search_origin: Option<(usize, usize)>,
+	last_search: Option<String>,
+	dirty: bool,
+	quit_times: u8,
+	config: Config,
+	number_mode: NumberMode,
+	tab_stop: usize,
+	expand_tab: bool,
+	/// Cached `(width, height)`, refreshed on `Event::Resize` so handlers
+	/// don't have to syscall `terminal_size()` on every keypress.
+	term_size: (u16, u16),
+	/// Set by `display_message`, shown on the message line until the next
+	/// keypress clears it. Kept separate from `command_buffer`/`search_buffer`
+	/// so a status message never gets mistaken for in-progress input.
+	status_message: String,
 }
 
+/// Default width a `\t` expands to when rendered, following common editor
+/// convention.
+const DEFAULT_TAB_STOP: usize = 4;
+
 impl Editor {
 	fn new() -> Self {
 		Editor {
 			mode: Mode::Normal,
 			cursor_x: 0,
 			cursor_y: 0,
-			buffer: vec![String::new()],
+			buffer: Rope::from_str("\n"),
 			command_buffer: String::new(),
 			filename: None,
 			scroll_x: 0,
 			scroll_y: 0,
+			undo_stack: Vec::new(),
+			redo_stack: Vec::new(),
+			undo_limit: UNDO_LIMIT,
+			search_buffer: String::new(),
+			search_origin: None,
+			last_search: None,
+			dirty: false,
+			quit_times: QUIT_TIMES,
+			config: config::load(),
+			number_mode: NumberMode::Off,
+			tab_stop: DEFAULT_TAB_STOP,
+			expand_tab: false,
+			term_size: terminal_size().unwrap_or((80, 24)),
+			status_message: String::new(),
+		}
+	}
+
+	/// Expands `line(idx)`'s tabs into spaces up to the next `tab_stop`
+	/// boundary, for display only — the authoritative text still has `\t`.
+	fn render_line(&self, idx: usize) -> String {
+		let mut out = String::new();
+		for c in self.line(idx).chars() {
+			if c == '\t' {
+				let spaces = self.tab_stop - (out.chars().count() % self.tab_stop);
+				for _ in 0..spaces {
+					out.push(' ');
+				}
+			} else {
+				out.push(c);
+			}
+		}
+		out
+	}
+
+	/// Maps a char offset on line `idx` to its column in `render_line(idx)`,
+	/// so the on-screen cursor lands correctly past any tabs.
+	fn render_x(&self, idx: usize, cursor_x: usize) -> usize {
+		let mut rx = 0;
+		for c in self.line(idx).chars().take(cursor_x) {
+			if c == '\t' {
+				rx += self.tab_stop - (rx % self.tab_stop);
+			} else {
+				rx += 1;
+			}
+		}
+		rx
+	}
+
+	/// Width of the left margin reserved for the gutter, including its
+	/// trailing separator space. Zero when numbering is off.
+	fn gutter_width(&self) -> usize {
+		if self.number_mode == NumberMode::Off {
+			return 0;
+		}
+		let lr_width = (self.len_lines() as u32).ilog10() as usize + 1;
+		lr_width + 1
+	}
+
+	/// Columns actually available for text, i.e. the terminal width minus
+	/// whatever the gutter is currently taking up.
+	fn text_width(&self) -> usize {
+		(self.term_size.0 as usize).saturating_sub(self.gutter_width())
+	}
+
+	fn snapshot(&self) -> Snapshot {
+		Snapshot {
+			buffer: self.buffer.clone(),
+			cursor_x: self.cursor_x,
+			cursor_y: self.cursor_y,
+		}
+	}
+
+	fn apply_snapshot(&mut self, snapshot: Snapshot) {
+		self.buffer = snapshot.buffer;
+		self.cursor_x = snapshot.cursor_x;
+		self.cursor_y = snapshot.cursor_y;
+	}
+
+	/// Records the current state for `u` to come back to, starting a fresh
+	/// coalescing group. Call this at the start of an insert run and before
+	/// any other destructive command, not on every keystroke.
+	fn push_undo(&mut self) {
+		self.undo_stack.push(self.snapshot());
+		if self.undo_stack.len() > self.undo_limit {
+			self.undo_stack.remove(0);
+		}
+		self.redo_stack.clear();
+	}
+
+	fn undo(&mut self) {
+		if let Some(snapshot) = self.undo_stack.pop() {
+			let current = self.snapshot();
+			self.apply_snapshot(snapshot);
+			self.redo_stack.push(current);
+		} else {
+			self.display_message(String::from("Already at oldest change"));
+		}
+	}
+
+	fn redo(&mut self) {
+		if let Some(snapshot) = self.redo_stack.pop() {
+			let current = self.snapshot();
+			self.apply_snapshot(snapshot);
+			self.undo_stack.push(current);
+		} else {
+			self.display_message(String::from("Already at newest change"));
 		}
 	}
 
 	fn display_message(&mut self, message: String) {
-		self.command_buffer = message;
+		self.status_message = message;
+	}
+
+	/// Text to overlay on the message line for the current mode, or `None`
+	/// if nothing should be shown there. `Mode::Command` always shows the
+	/// in-progress `:command`; every other mode shows `status_message` once
+	/// it's been set, so a warning set while still in Normal mode (like the
+	/// unsaved-changes quit prompt) doesn't vanish before `draw` runs again.
+	fn message_line(&self) -> Option<String> {
+		match self.mode {
+			Mode::Command => Some(format!(":{}", self.command_buffer)),
+			Mode::Search if !self.status_message.is_empty() => Some(self.status_message.clone()),
+			Mode::Search => Some(format!("/{}", self.search_buffer)),
+			_ if !self.status_message.is_empty() => Some(self.status_message.clone()),
+			_ => None,
+		}
+	}
+
+	/// Returns the text of line `idx` without its trailing newline.
+	fn line(&self, idx: usize) -> String {
+		let line = self.buffer.line(idx);
+		let line = line.to_string();
+		line.trim_end_matches(['\n', '\r']).to_string()
+	}
+
+	fn len_lines(&self) -> usize {
+		let len = self.buffer.len_lines();
+		// ropey counts a trailing empty line after a final '\n' as a line;
+		// an empty document still has one (empty) editable line.
+		if len > 1 && self.buffer.line(len - 1).len_chars() == 0 {
+			len - 1
+		} else {
+			len
+		}
+	}
+
+	/// Converts a `(line, column)` cursor position into a flat char offset.
+	fn char_idx(&self, line: usize, col: usize) -> usize {
+		self.buffer.line_to_char(line) + col
+	}
+
+	fn insert_char(&mut self, line: usize, col: usize, c: char) {
+		let idx = self.char_idx(line, col);
+		self.buffer.insert_char(idx, c);
+	}
+
+	fn remove_char(&mut self, line: usize, col: usize) {
+		let idx = self.char_idx(line, col);
+		self.buffer.remove(idx..idx + 1);
 	}
 
 	fn load_file(&mut self, filename: &str) {
 		if let Ok(contents) = fs::read_to_string(filename) {
-			self.buffer = contents.lines().map(|line| line.to_string()).collect();
+			self.buffer = Rope::from_str(&contents);
+			if self.buffer.len_chars() == 0 || !contents.ends_with('\n') {
+				self.buffer.insert_char(self.buffer.len_chars(), '\n');
+			}
 			self.filename = Some(filename.to_string());
 		} else {
-			self.buffer = vec![String::new()];
+			self.buffer = Rope::from_str("\n");
 			self.filename = Some(filename.to_string());
 		}
+		self.dirty = false;
 	}
 
 	fn save_file(&mut self) {
@@ -63,12 +297,14 @@ impl Editor {
 				.open(filename)
 				.unwrap();
 
-			for (i, line) in self.buffer.iter().enumerate() {
-				write!(file, "{}", line).unwrap();
-				if i < self.buffer.len() - 1 {
-					write!(file, "\n").unwrap();
+			let len_lines = self.len_lines();
+			for i in 0..len_lines {
+				write!(file, "{}", self.line(i)).unwrap();
+				if i < len_lines - 1 {
+					writeln!(file).unwrap();
 				}
 			}
+			self.dirty = false;
 		} else {
 			self.display_message(String::from("Error: no file to write"));
 		}
@@ -79,68 +315,368 @@ impl Editor {
 			Mode::Normal => self.handle_normal_mode(key),
 			Mode::Insert => self.handle_insert_mode(key),
 			Mode::Command => self.handle_command_mode(key),
+			Mode::Search => self.handle_search_mode(key),
 		}
 	}
 
+	/// Looks `key` up in the configured keymap and runs the bound action, if
+	/// any. This is the only place Normal-mode keys are dispatched, so
+	/// remapping a key in the config is enough to change its behavior here.
 	fn handle_normal_mode(&mut self, key: Key) {
-		match key {
-			Key::Char('i') => self.mode = Mode::Insert,
-			Key::Char(':') => {
-				self.mode = Mode::Command;
-				self.command_buffer.clear();
-			}
-			Key::Char('h') => {
-				if self.cursor_x > 0 {
-					self.cursor_x -= 1;
-				} else if self.scroll_x > 0 {
-					self.scroll_x -= 1;
+		self.status_message.clear();
+
+		if !matches!(key, Key::Char(':')) {
+			self.quit_times = QUIT_TIMES;
+		}
+
+		if let Some(action) = self.config.keymap.get(&key).copied() {
+			action(self);
+		}
+	}
+
+	fn move_left(&mut self) {
+		if self.cursor_x > 0 {
+			self.cursor_x -= 1;
+		} else if self.scroll_x > 0 {
+			self.scroll_x -= 1;
+		}
+	}
+
+	fn move_right(&mut self) {
+		if self.cursor_x < self.line(self.cursor_y).chars().count() {
+			self.cursor_x += 1;
+		} else if self.render_x(self.cursor_y, self.cursor_x) >= self.text_width() {
+			self.scroll_x += 1;
+		}
+	}
+
+	fn move_up(&mut self) {
+		if self.cursor_y > self.scroll_y {
+			self.cursor_y -= 1;
+			self.cursor_x = self.line(self.cursor_y).chars().count().min(self.cursor_x);
+		} else if self.scroll_y > 0 {
+			self.scroll_y -= 1;
+		}
+	}
+
+	fn move_down(&mut self) {
+		if self.cursor_y + 1 < self.len_lines() {
+			self.cursor_y += 1;
+			self.cursor_x = self.line(self.cursor_y).chars().count().min(self.cursor_x);
+		} else if self.cursor_y < self.len_lines()
+			&& self.cursor_y >= self.term_size.1 as usize
+		{
+			self.scroll_y += 1;
+		}
+	}
+
+	fn enter_insert_mode(&mut self) {
+		self.push_undo();
+		self.mode = Mode::Insert;
+	}
+
+	fn enter_command_mode(&mut self) {
+		self.mode = Mode::Command;
+		self.command_buffer.clear();
+	}
+
+	fn enter_search_mode(&mut self) {
+		self.mode = Mode::Search;
+		self.search_buffer.clear();
+		self.search_origin = Some((self.cursor_x, self.cursor_y));
+	}
+
+	fn search_next_forward(&mut self) {
+		self.search_next(true);
+	}
+
+	fn search_next_backward(&mut self) {
+		self.search_next(false);
+	}
+
+	/// Scans for `query` from `from` without wrapping, moving forward or
+	/// backward through the buffer one line at a time. A forward scan can
+	/// match starting at `from`; a backward scan only finds matches that end
+	/// before `from`.
+	fn find_match(&self, query: &str, from: (usize, usize), forward: bool) -> Option<(usize, usize)> {
+		if query.is_empty() {
+			return None;
+		}
+		let (from_x, from_y) = from;
+		let len_lines = self.len_lines();
+
+		if forward {
+			for y in from_y..len_lines {
+				let chars: Vec<char> = self.line(y).chars().collect();
+				let start_col = if y == from_y { from_x } else { 0 };
+				if start_col > chars.len() {
+					continue;
 				}
-			}
-			Key::Char('l') => {
-				if self.cursor_x < self.buffer[self.cursor_y].len() {
-					self.cursor_x += 1;
-				} else if self.cursor_x >= terminal_size().unwrap().0 as usize {
-					self.scroll_x += 1;
+				let suffix: String = chars[start_col..].iter().collect();
+				if let Some(byte_offset) = suffix.find(query) {
+					let char_offset = suffix[..byte_offset].chars().count();
+					return Some((start_col + char_offset, y));
 				}
 			}
-			Key::Char('k') => {
-				if self.cursor_y > self.scroll_y {
-					self.cursor_y -= 1;
-					self.cursor_x = self.buffer[self.cursor_y].len().min(self.cursor_x);
-				} else if self.scroll_y > 0 {
-					self.scroll_y -= 1;
+		} else {
+			for y in (0..=from_y).rev() {
+				let chars: Vec<char> = self.line(y).chars().collect();
+				let end_col = if y == from_y { from_x } else { chars.len() };
+				let end_col = end_col.min(chars.len());
+				let prefix: String = chars[..end_col].iter().collect();
+				if let Some(byte_offset) = prefix.rfind(query) {
+					let char_offset = prefix[..byte_offset].chars().count();
+					return Some((char_offset, y));
 				}
 			}
-			Key::Char('j') => {
-				if self.cursor_y + 1 < self.buffer.len() {
-					self.cursor_y += 1;
-					self.cursor_x = self.buffer[self.cursor_y].len().min(self.cursor_x);
-				} else if self.cursor_y < self.buffer.len()
-					&& self.cursor_y >= terminal_size().unwrap().1 as usize
-				{
-					self.scroll_y += 1;
+		}
+
+		None
+	}
+
+	/// Re-scans from `search_origin` using the in-progress query, used on
+	/// every keystroke while in `Mode::Search`.
+	fn search_rescan(&mut self) {
+		let origin = self.search_origin.unwrap_or((self.cursor_x, self.cursor_y));
+		if self.search_buffer.is_empty() {
+			self.cursor_x = origin.0;
+			self.cursor_y = origin.1;
+			self.scroll_into_view();
+			return;
+		}
+		match self.find_match(&self.search_buffer.clone(), origin, true) {
+			Some((x, y)) => {
+				self.cursor_x = x;
+				self.cursor_y = y;
+				self.scroll_into_view();
+			}
+			None => self.display_message(String::from("pattern not found")),
+		}
+	}
+
+	/// Jumps to the next (or, if `forward` is false, previous) match of
+	/// `last_search`, wrapping around the document end/start.
+	fn search_next(&mut self, forward: bool) {
+		let query = match self.last_search.clone() {
+			Some(query) => query,
+			None => {
+				self.display_message(String::from("No previous search"));
+				return;
+			}
+		};
+
+		let from = if forward {
+			(self.cursor_x + 1, self.cursor_y)
+		} else {
+			(self.cursor_x, self.cursor_y)
+		};
+
+		let wrapped_from = if forward {
+			(0, 0)
+		} else {
+			let last_y = self.len_lines().saturating_sub(1);
+			(self.line(last_y).chars().count(), last_y)
+		};
+
+		let found = self
+			.find_match(&query, from, forward)
+			.or_else(|| self.find_match(&query, wrapped_from, forward));
+
+		match found {
+			Some((x, y)) => {
+				self.cursor_x = x;
+				self.cursor_y = y;
+				self.scroll_into_view();
+			}
+			None => self.display_message(format!("pattern not found: {}", query)),
+		}
+	}
+
+	fn handle_search_mode(&mut self, key: Key) {
+		self.status_message.clear();
+
+		match key {
+			Key::Esc => {
+				if let Some((x, y)) = self.search_origin.take() {
+					self.cursor_x = x;
+					self.cursor_y = y;
+					self.scroll_into_view();
 				}
+				self.mode = Mode::Normal;
+			}
+			Key::Char('\n') => {
+				self.last_search = if self.search_buffer.is_empty() {
+					None
+				} else {
+					Some(self.search_buffer.clone())
+				};
+				self.search_origin = None;
+				self.mode = Mode::Normal;
+			}
+			Key::Char(c) => {
+				self.search_buffer.push(c);
+				self.search_rescan();
+			}
+			Key::Backspace => {
+				self.search_buffer.pop();
+				self.search_rescan();
 			}
 			_ => {}
 		}
 	}
 
+	/// Brings the cursor back into the visible viewport, mirroring the
+	/// scroll adjustment `j`/`k` already do but usable after a jump of more
+	/// than one line or column.
+	fn scroll_into_view(&mut self) {
+		let term_width = self.text_width();
+		let term_height = self.term_size.1 as usize;
+
+		if self.cursor_y < self.scroll_y {
+			self.scroll_y = self.cursor_y;
+		} else if self.cursor_y >= self.scroll_y + term_height - 2 {
+			self.scroll_y = self.cursor_y - (term_height - 2) + 1;
+		}
+
+		let render_x = self.render_x(self.cursor_y, self.cursor_x);
+		if render_x < self.scroll_x {
+			self.scroll_x = render_x;
+		} else if render_x >= self.scroll_x + term_width {
+			self.scroll_x = render_x - term_width + 1;
+		}
+	}
+
+	fn move_next_word_start(&mut self) {
+		let mut x = self.cursor_x;
+		let mut y = self.cursor_y;
+		let mut chars: Vec<char> = self.line(y).chars().collect();
+
+		if x < chars.len() {
+			let start_class = char_class(chars[x]);
+			while x < chars.len() && char_class(chars[x]) == start_class {
+				x += 1;
+			}
+		}
+		loop {
+			while x < chars.len() && char_class(chars[x]) == CharClass::Whitespace {
+				x += 1;
+			}
+			if x < chars.len() {
+				break;
+			}
+			if y + 1 >= self.len_lines() {
+				break;
+			}
+			y += 1;
+			x = 0;
+			chars = self.line(y).chars().collect();
+		}
+
+		self.cursor_y = y;
+		self.cursor_x = x.min(chars.len());
+		self.scroll_into_view();
+	}
+
+	fn move_prev_word_start(&mut self) {
+		let mut x = self.cursor_x;
+		let mut y = self.cursor_y;
+		let mut chars: Vec<char> = self.line(y).chars().collect();
+
+		if x > 0 {
+			x -= 1;
+		} else if y > 0 {
+			y -= 1;
+			chars = self.line(y).chars().collect();
+			x = chars.len().saturating_sub(1);
+		}
+
+		while !chars.is_empty() && char_class(chars[x]) == CharClass::Whitespace {
+			if x > 0 {
+				x -= 1;
+			} else if y > 0 {
+				y -= 1;
+				chars = self.line(y).chars().collect();
+				x = chars.len().saturating_sub(1);
+			} else {
+				break;
+			}
+		}
+
+		if !chars.is_empty() {
+			let class = char_class(chars[x]);
+			while x > 0 && char_class(chars[x - 1]) == class {
+				x -= 1;
+			}
+		}
+
+		self.cursor_y = y;
+		self.cursor_x = x;
+		self.scroll_into_view();
+	}
+
+	fn move_next_word_end(&mut self) {
+		let mut x = self.cursor_x;
+		let mut y = self.cursor_y;
+		let mut chars: Vec<char> = self.line(y).chars().collect();
+
+		loop {
+			// Step to the next position, wrapping onto the next line when
+			// this one is exhausted. Mirrors move_prev_word_start: step
+			// first, then judge the landed-on position — never require
+			// room to step *again* before a single-char line counts.
+			if x + 1 < chars.len() {
+				x += 1;
+			} else if y + 1 < self.len_lines() {
+				y += 1;
+				x = 0;
+				chars = self.line(y).chars().collect();
+			} else {
+				break;
+			}
+
+			if chars.is_empty() || char_class(chars[x]) == CharClass::Whitespace {
+				continue;
+			}
+
+			let class = char_class(chars[x]);
+			while x + 1 < chars.len() && char_class(chars[x + 1]) == class {
+				x += 1;
+			}
+			break;
+		}
+
+		self.cursor_y = y;
+		self.cursor_x = x.min(chars.len().saturating_sub(1));
+		self.scroll_into_view();
+	}
+
 	fn handle_insert_mode(&mut self, key: Key) {
 		match key {
 			Key::Esc => self.mode = Mode::Normal,
 			Key::Char('\n') => {
-				let remaining_line = self.buffer[self.cursor_y].split_off(self.cursor_x);
-				self.buffer.insert(self.cursor_y + 1, remaining_line);
+				self.push_undo();
+				let idx = self.char_idx(self.cursor_y, self.cursor_x);
+				self.buffer.insert_char(idx, '\n');
 				self.cursor_y += 1;
 				self.cursor_x = 0;
-				if self.cursor_y >= terminal_size().unwrap().1 as usize - 2 {
+				self.dirty = true;
+				if self.cursor_y >= self.term_size.1 as usize - 2 {
 					self.scroll_y += 1;
 				}
 			}
+			Key::Char('\t') if self.expand_tab => {
+				let spaces = self.tab_stop - (self.cursor_x % self.tab_stop);
+				for _ in 0..spaces {
+					self.insert_char(self.cursor_y, self.cursor_x, ' ');
+					self.cursor_x += 1;
+				}
+				self.dirty = true;
+			}
 			Key::Char(c) => {
-				self.buffer[self.cursor_y].insert(self.cursor_x, c);
+				self.insert_char(self.cursor_y, self.cursor_x, c);
 				self.cursor_x += 1;
-				if self.cursor_x >= terminal_size().unwrap().0 as usize {
+				self.dirty = true;
+				if self.render_x(self.cursor_y, self.cursor_x) >= self.text_width() {
 					self.scroll_x += 1;
 				}
 			}
@@ -150,13 +686,16 @@ impl Editor {
 						self.scroll_x -= 1;
 					}
 					self.cursor_x -= 1;
-					self.buffer[self.cursor_y].remove(self.cursor_x);
+					self.remove_char(self.cursor_y, self.cursor_x);
+					self.dirty = true;
 				} else if self.cursor_y > 0 {
-					let prev_line_length = self.buffer[self.cursor_y - 1].len();
-					let current_line = self.buffer.remove(self.cursor_y);
+					let prev_line_length = self.line(self.cursor_y - 1).chars().count();
+					let idx = self.char_idx(self.cursor_y, 0);
+					// Remove the newline joining the previous line to this one.
+					self.buffer.remove(idx - 1..idx);
 					self.cursor_y -= 1;
 					self.cursor_x = prev_line_length;
-					self.buffer[self.cursor_y].push_str(&current_line);
+					self.dirty = true;
 				}
 			}
 			_ => {}
@@ -177,8 +716,21 @@ impl Editor {
 
 	fn execute_command(&mut self) {
 		let command = self.command_buffer.clone();
+		if command.trim() != "q" {
+			self.quit_times = QUIT_TIMES;
+		}
 		match command.trim() {
-			"q" => process::exit(0),
+			"q" => {
+				if self.dirty && self.quit_times > 0 {
+					self.quit_times -= 1;
+					self.display_message(String::from(
+						"Unsaved changes — press :q again to quit",
+					));
+				} else {
+					process::exit(0);
+				}
+			}
+			"q!" => process::exit(0),
 			_ if command.starts_with("w ") => {
 				self.filename = Some(command.split_at(2).1.trim().to_string());
 				self.save_file();
@@ -192,40 +744,91 @@ impl Editor {
 				let filename = command.split_at(2).1.trim();
 				self.load_file(filename);
 			}
+			"set number" => self.number_mode = NumberMode::Absolute,
+			"set relativenumber" => self.number_mode = NumberMode::Relative,
+			"set nonumber" => self.number_mode = NumberMode::Off,
+			"set expandtab" => self.expand_tab = true,
+			"set noexpandtab" => self.expand_tab = false,
+			_ if command.trim().starts_with("set tabstop ") => {
+				if let Ok(width) = command.trim()[12..].trim().parse::<usize>() {
+					if width > 0 {
+						self.tab_stop = width;
+					}
+				}
+			}
 			_ => {}
 		}
 		self.mode = Mode::Normal;
 	}
 
 	fn draw(&self, stdout: &mut io::Stdout) {
-		let (term_width, term_height) = terminal_size().unwrap();
-		let term_height = term_height as usize;
-		let term_width = term_width as usize;
+		let term_width = self.term_size.0 as usize;
+		let term_height = self.term_size.1 as usize;
 
 		write!(stdout, "{}", termion::clear::All).unwrap();
 
+		let gutter_width = self.gutter_width();
+		let text_width = term_width.saturating_sub(gutter_width);
+		let lr_width = gutter_width.saturating_sub(1);
+
 		let start_line = self.scroll_y;
-		let end_line = (self.scroll_y + term_height - 2).min(self.buffer.len());
+		let end_line = (self.scroll_y + term_height - 2).min(self.len_lines());
 
-		for (i, line) in self.buffer[start_line..end_line].iter().enumerate() {
-			let visible_line = if self.scroll_x < line.len() {
-				&line[self.scroll_x..(self.scroll_x + term_width).min(line.len())]
+		for i in start_line..end_line {
+			let line = self.render_line(i);
+			let chars: Vec<char> = line.chars().collect();
+			let visible_line: String = if self.scroll_x < chars.len() {
+				chars[self.scroll_x..(self.scroll_x + text_width).min(chars.len())]
+					.iter()
+					.collect()
 			} else {
-				""
+				String::new()
+			};
+
+			let gutter = match self.number_mode {
+				NumberMode::Off => String::new(),
+				NumberMode::Absolute => format!("{:>width$} ", i + 1, width = lr_width),
+				NumberMode::Relative => {
+					let number = if i == self.cursor_y {
+						i + 1
+					} else {
+						(i as isize - self.cursor_y as isize).unsigned_abs()
+					};
+					format!("{:>width$} ", number, width = lr_width)
+				}
 			};
-			write!(stdout, "{}{}", cursor::Goto(1, i as u16 + 1), visible_line).unwrap();
+
+			write!(stdout, "{}", cursor::Goto(1, (i - start_line) as u16 + 1)).unwrap();
+			if gutter_width > 0 {
+				write!(
+					stdout,
+					"{}{}{}{}{}",
+					color::Fg(color::AnsiValue(self.config.theme.gutter_fg)),
+					color::Bg(color::AnsiValue(self.config.theme.gutter_bg)),
+					gutter,
+					color::Fg(color::Reset),
+					color::Bg(color::Reset)
+				)
+				.unwrap();
+			}
+			write!(stdout, "{}", visible_line).unwrap();
 		}
 
 		let status_line = format!(
-			" {:?} @ {}",
+			" {:?} @ {}{}",
 			self.mode,
-			self.filename.clone().unwrap_or("[No Name]".to_string())
+			self.filename.clone().unwrap_or("[No Name]".to_string()),
+			if self.dirty { " [+]" } else { "" }
 		);
 		write!(
 			stdout,
-			"{}{}",
+			"{}{}{}{}{}{}",
 			cursor::Goto(1, term_height as u16),
-			status_line
+			color::Fg(color::AnsiValue(self.config.theme.status_fg)),
+			color::Bg(color::AnsiValue(self.config.theme.status_bg)),
+			status_line,
+			color::Fg(color::Reset),
+			color::Bg(color::Reset)
 		)
 		.unwrap();
 
@@ -233,26 +836,54 @@ impl Editor {
 			stdout,
 			"{}",
 			cursor::Goto(
-				(self.cursor_x - self.scroll_x + 1) as u16,
+				(self.render_x(self.cursor_y, self.cursor_x) - self.scroll_x + 1 + gutter_width) as u16,
 				(self.cursor_y - self.scroll_y + 1) as u16
 			)
 		)
 		.unwrap();
 
-		if let Mode::Command = self.mode {
-			write!(
-				stdout,
-				"{}:{}",
-				cursor::Goto(1, (term_height - 1) as u16),
-				self.command_buffer
-			)
-			.unwrap();
+		if let Some(line) = self.message_line() {
+			write!(stdout, "{}{}", cursor::Goto(1, (term_height - 1) as u16), line).unwrap();
 		}
 
 		stdout.flush().unwrap();
 	}
 }
 
+/// Unifies key input and terminal resizes so the main loop can redraw
+/// immediately on either, instead of only noticing a resize on the next
+/// keypress.
+enum Event {
+	Key(Key),
+	Resize(u16, u16),
+}
+
+/// Forwards each key the user types as an `Event::Key` on `tx`.
+fn spawn_key_reader(tx: mpsc::Sender<Event>) {
+	thread::spawn(move || {
+		let stdin = io::stdin();
+		for key in stdin.keys().flatten() {
+			if tx.send(Event::Key(key)).is_err() {
+				break;
+			}
+		}
+	});
+}
+
+/// Forwards each `SIGWINCH` as an `Event::Resize` on `tx`.
+fn spawn_resize_watcher(tx: mpsc::Sender<Event>) {
+	thread::spawn(move || {
+		let mut signals = Signals::new([SIGWINCH]).unwrap();
+		for _ in signals.forever() {
+			if let Ok((width, height)) = terminal_size() {
+				if tx.send(Event::Resize(width, height)).is_err() {
+					break;
+				}
+			}
+		}
+	});
+}
+
 fn main() {
 	let mut editor = Editor::new();
 
@@ -261,14 +892,142 @@ fn main() {
 		editor.load_file(&filename);
 	}
 
-	let stdin = io::stdin();
 	let mut stdout = io::stdout().into_raw_mode().unwrap();
-	let mut keys = stdin.keys();
 
-	loop {
-		editor.draw(&mut stdout);
-		if let Some(Ok(key)) = keys.next() {
-			editor.process_key(key);
+	let (tx, rx) = mpsc::channel();
+	spawn_key_reader(tx.clone());
+	spawn_resize_watcher(tx);
+
+	editor.draw(&mut stdout);
+	for event in rx {
+		match event {
+			Event::Key(key) => editor.process_key(key),
+			Event::Resize(width, height) => editor.term_size = (width, height),
 		}
+		editor.draw(&mut stdout);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Guards against the message line going blank right when it matters
+	/// most: `display_message` sets `status_message` while still in Normal
+	/// mode (as the `:q` unsaved-changes prompt does), and `message_line`
+	/// must still surface it on the very next `draw`.
+	#[test]
+	fn normal_mode_status_message_is_visible() {
+		let mut editor = Editor::new();
+		editor.display_message(String::from("Unsaved changes — press :q again to quit"));
+		assert_eq!(
+			editor.message_line(),
+			Some(String::from("Unsaved changes — press :q again to quit"))
+		);
+	}
+
+	#[test]
+	fn command_mode_shows_command_buffer_not_status_message() {
+		let mut editor = Editor::new();
+		editor.mode = Mode::Command;
+		editor.command_buffer = String::from("w");
+		editor.status_message = String::from("stale message");
+		assert_eq!(editor.message_line(), Some(String::from(":w")));
+	}
+
+	/// `u`/`Ctrl-r` are Normal-mode keys, so their boundary messages hit the
+	/// same invisible-status-message bug as the `:q` warning — pin them down
+	/// now that `message_line` renders outside Command/Search too.
+	#[test]
+	fn undo_at_oldest_change_shows_message() {
+		let mut editor = Editor::new();
+		editor.undo();
+		assert_eq!(editor.message_line(), Some(String::from("Already at oldest change")));
+	}
+
+	#[test]
+	fn redo_at_newest_change_shows_message() {
+		let mut editor = Editor::new();
+		editor.redo();
+		assert_eq!(editor.message_line(), Some(String::from("Already at newest change")));
+	}
+
+	/// `n`/`N` also run in Normal mode, same as undo/redo above.
+	#[test]
+	fn search_next_with_no_previous_search_shows_message() {
+		let mut editor = Editor::new();
+		editor.search_next_forward();
+		assert_eq!(editor.message_line(), Some(String::from("No previous search")));
+	}
+
+	#[test]
+	fn search_next_with_no_match_shows_message() {
+		let mut editor = Editor::new();
+		editor.last_search = Some(String::from("xyz"));
+		editor.search_next_forward();
+		assert_eq!(editor.message_line(), Some(String::from("pattern not found: xyz")));
+	}
+
+	/// Unlike the cases above, this message is set while `mode` is still
+	/// `Mode::Search` (every keystroke re-runs `search_rescan`), so it was
+	/// hidden by a second bug: `message_line`'s Search arm used to always
+	/// render `search_buffer`, never `status_message`.
+	#[test]
+	fn search_rescan_no_match_message_visible_while_still_searching() {
+		let mut editor = Editor::new();
+		editor.mode = Mode::Search;
+		editor.search_origin = Some((0, 0));
+		editor.search_buffer = String::from("xyz");
+		editor.search_rescan();
+		assert_eq!(editor.message_line(), Some(String::from("pattern not found")));
+	}
+
+	fn editor_with_lines(lines: &[&str]) -> Editor {
+		let mut editor = Editor::new();
+		editor.buffer = Rope::from_str(&format!("{}\n", lines.join("\n")));
+		editor
+	}
+
+	#[test]
+	fn word_end_lands_on_single_char_word_across_line_wraps() {
+		let mut editor = editor_with_lines(&["ab", "c", "de"]);
+		editor.cursor_x = 1;
+		editor.cursor_y = 0;
+
+		editor.move_next_word_end();
+		assert_eq!((editor.cursor_x, editor.cursor_y), (0, 1));
+
+		editor.move_next_word_end();
+		assert_eq!((editor.cursor_x, editor.cursor_y), (1, 2));
+	}
+
+	#[test]
+	fn word_end_extends_across_a_multi_char_word() {
+		let mut editor = editor_with_lines(&["abc def"]);
+		editor.cursor_x = 0;
+		editor.cursor_y = 0;
+
+		editor.move_next_word_end();
+		assert_eq!((editor.cursor_x, editor.cursor_y), (2, 0));
+	}
+
+	#[test]
+	fn word_start_skips_whitespace_onto_an_indented_line() {
+		let mut editor = editor_with_lines(&["foo", "    bar"]);
+		editor.cursor_x = 0;
+		editor.cursor_y = 0;
+
+		editor.move_next_word_start();
+		assert_eq!((editor.cursor_x, editor.cursor_y), (4, 1));
+	}
+
+	#[test]
+	fn word_prev_start_moves_back_to_the_previous_word() {
+		let mut editor = editor_with_lines(&["foo bar"]);
+		editor.cursor_x = 4;
+		editor.cursor_y = 0;
+
+		editor.move_prev_word_start();
+		assert_eq!((editor.cursor_x, editor.cursor_y), (0, 0));
 	}
 }